@@ -2,12 +2,16 @@
 //!
 //! These API endpoints are used to manage users and roles.
 
+use std::fmt;
 use std::str::FromStr;
 
-use futures::{Future, IntoFuture, Stream};
+use futures::{future, Future, IntoFuture, Stream};
 use hyper::{StatusCode, Uri};
 use hyper::client::Connect;
+use serde::{Serialize, Serializer};
+use serde::de::DeserializeOwned;
 use serde_json;
+use zeroize::Zeroize;
 
 use async::first_ok;
 use client::{Client, ClusterInfo, Response};
@@ -83,13 +87,68 @@ impl User {
     }
 }
 
+/// A password held in memory only as long as it is needed, and scrubbed from the heap when it
+/// is dropped or replaced, so a stray credential does not linger in freed memory.
+#[derive(Clone, Eq, Hash, PartialEq)]
+pub struct Password(String);
+
+impl Password {
+    /// Wraps a password.
+    pub fn new<P>(password: P) -> Self
+    where
+        P: Into<String>,
+    {
+        Password(password.into())
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+impl Serialize for Password {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl Drop for Password {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod password_tests {
+    use super::Password;
+
+    #[test]
+    fn debug_redacts_the_password() {
+        let password = Password::new("super-secret");
+
+        assert_eq!(format!("{:?}", password), "<redacted>");
+    }
+
+    #[test]
+    fn serializes_as_the_plain_password_string() {
+        let password = Password::new("super-secret");
+
+        assert_eq!(::serde_json::to_string(&password).unwrap(), "\"super-secret\"");
+    }
+}
+
 /// Paramters used to create a new etcd user.
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize)]
 pub struct NewUser {
     /// The user's name.
     name: String,
     /// The user's password.
-    password: String,
+    password: Password,
     /// An initial set of roles granted to the user.
     roles: Option<Vec<String>>,
 }
@@ -103,7 +162,7 @@ impl NewUser {
     {
         NewUser {
             name: name.into(),
-            password: password.into(),
+            password: Password::new(password),
             roles: None,
         }
     }
@@ -126,7 +185,7 @@ pub struct UserUpdate {
     /// The user's name.
     name: String,
     /// A new password for the user.
-    password: Option<String>,
+    password: Option<Password>,
     /// Roles being granted to the user.
     #[serde(rename = "grant")]
     grants: Option<Vec<String>>,
@@ -154,7 +213,7 @@ impl UserUpdate {
     where
         P: Into<String>,
     {
-        self.password = Some(password.into());
+        self.password = Some(Password::new(password));
     }
 
     /// Grants the given role to the user.
@@ -201,6 +260,32 @@ impl Role {
         }
     }
 
+    /// Adds a parent role that this role inherits permissions from.
+    ///
+    /// etcd's role schema has no native concept of role inheritance, so the parent's name is
+    /// persisted as an ordinary (if unusual-looking) kv read permission, using a prefix that
+    /// cannot collide with a real etcd key. This is invisible to `kv_read_permissions` and
+    /// `allows_kv_read`, and is written and re-read through `create_role`/`get_role` exactly like
+    /// any other read permission. To add a parent to an existing role, use
+    /// `RoleUpdate::grant_parent` with `update_role`.
+    pub fn add_parent<R>(&mut self, parent: R)
+    where
+        R: Into<String>,
+    {
+        self.permissions.kv.add_read_permission(parent_marker(parent.into()))
+    }
+
+    /// Returns the names of the roles this role inherits permissions from.
+    pub fn parents(&self) -> Vec<String> {
+        self.permissions
+            .kv
+            .read
+            .iter()
+            .filter_map(|entry| parent_name(entry))
+            .map(str::to_owned)
+            .collect()
+    }
+
     /// Grants read permission for a key in etcd's key-value store to this role.
     pub fn add_kv_read_permission<K>(&mut self, key: K)
     where
@@ -217,15 +302,51 @@ impl Role {
         self.permissions.kv.add_write_permission(key)
     }
 
+    /// Grants read permission for every key sharing the given prefix in etcd's key-value store
+    /// to this role.
+    pub fn add_kv_read_prefix_permission<K>(&mut self, prefix: K)
+    where
+        K: Into<String>,
+    {
+        self.permissions.kv.add_read_permission(prefix_pattern(prefix))
+    }
+
+    /// Grants write permission for every key sharing the given prefix in etcd's key-value store
+    /// to this role.
+    pub fn add_kv_write_prefix_permission<K>(&mut self, prefix: K)
+    where
+        K: Into<String>,
+    {
+        self.permissions.kv.add_write_permission(prefix_pattern(prefix))
+    }
+
     /// Returns a list of keys in etcd's key-value store that this role is allowed to read.
-    pub fn kv_read_permissions(&self) -> &[String] {
-        &self.permissions.kv.read
+    pub fn kv_read_permissions(&self) -> Vec<String> {
+        self.permissions
+            .kv
+            .read
+            .iter()
+            .filter(|entry| parent_name(entry).is_none())
+            .cloned()
+            .collect()
     }
 
     /// Returns a list of keys in etcd's key-value store that this role is allowed to write.
     pub fn kv_write_permissions(&self) -> &[String] {
         &self.permissions.kv.write
     }
+
+    /// Determines whether this role grants read access to the given key, taking prefix grants
+    /// (permissions ending in `*`) into account.
+    pub fn allows_kv_read(&self, key: &str) -> bool {
+        self.permissions.kv.allows_read(key)
+    }
+
+    /// Determines whether this role grants write access to the given key, taking prefix grants
+    /// (permissions ending in `*`) into account.
+    pub fn allows_kv_write(&self, key: &str) -> bool {
+        self.permissions.kv.allows_write(key)
+    }
 }
 
 /// Parameters used to update an existing authorization role.
@@ -254,6 +375,26 @@ impl RoleUpdate {
         }
     }
 
+    /// Grants a parent role that this role will inherit permissions from.
+    ///
+    /// As with `Role::add_parent`, this is encoded as an ordinary granted read permission
+    /// (see `parent_marker`) since etcd has no native notion of role inheritance to send it
+    /// through instead.
+    pub fn grant_parent<R>(&mut self, parent: R)
+    where
+        R: Into<String>,
+    {
+        self.grants.kv.add_read_permission(parent_marker(parent.into()))
+    }
+
+    /// Revokes a parent role that this role previously inherited permissions from.
+    pub fn revoke_parent<R>(&mut self, parent: R)
+    where
+        R: Into<String>,
+    {
+        self.revocations.kv.add_read_permission(parent_marker(parent.into()))
+    }
+
     /// Grants read permission for a key in etcd's key-value store to this role.
     pub fn grant_kv_read_permission<K>(&mut self, key: K)
     where
@@ -270,6 +411,24 @@ impl RoleUpdate {
         self.grants.kv.add_write_permission(key)
     }
 
+    /// Grants read permission for every key sharing the given prefix in etcd's key-value store
+    /// to this role.
+    pub fn grant_kv_read_prefix_permission<K>(&mut self, prefix: K)
+    where
+        K: Into<String>,
+    {
+        self.grants.kv.add_read_permission(prefix_pattern(prefix))
+    }
+
+    /// Grants write permission for every key sharing the given prefix in etcd's key-value store
+    /// to this role.
+    pub fn grant_kv_write_prefix_permission<K>(&mut self, prefix: K)
+    where
+        K: Into<String>,
+    {
+        self.grants.kv.add_write_permission(prefix_pattern(prefix))
+    }
+
     /// Revokes read permission for a key in etcd's key-value store from this role.
     pub fn revoke_kv_read_permission<K>(&mut self, key: &K)
     where
@@ -289,6 +448,42 @@ impl RoleUpdate {
     }
 }
 
+#[cfg(test)]
+mod role_parent_persistence_tests {
+    use super::{Role, RoleUpdate};
+
+    #[test]
+    fn parent_set_via_add_parent_survives_the_wire_round_trip() {
+        // etcd's own `Role` JSON schema has no `parents` field, so this exercises the same
+        // `Role` serialize/deserialize path `create_role`/`get_role` use, to show that a parent
+        // recorded this way is carried in the ordinary JSON fields and not dropped by it.
+        let mut role = Role::new("child");
+        role.add_kv_read_permission("/app");
+        role.add_parent("parent-role");
+
+        let wire = ::serde_json::to_string(&role).unwrap();
+        let round_tripped: Role = ::serde_json::from_str(&wire).unwrap();
+
+        assert_eq!(round_tripped.parents(), vec!["parent-role".to_owned()]);
+        assert_eq!(round_tripped.kv_read_permissions(), vec!["/app".to_owned()]);
+    }
+
+    #[test]
+    fn grant_parent_is_sent_as_an_ordinary_kv_read_grant() {
+        // `RoleUpdate::grant_parent` has to travel to the server through the same `grant.kv.read`
+        // list any other read permission does, since etcd has no `grantParents` concept to send
+        // it through instead.
+        let mut update = RoleUpdate::new("child");
+        update.grant_parent("parent-role");
+
+        let wire = ::serde_json::to_value(&update).unwrap();
+        let granted_reads = wire["grant"]["kv"]["read"].as_array().unwrap();
+
+        assert_eq!(granted_reads.len(), 1);
+        assert!(granted_reads[0].as_str().unwrap().ends_with("parent-role"));
+    }
+}
+
 /// The access permissions granted to a role.
 #[derive(Debug, Deserialize, Clone, Eq, Hash, PartialEq, Serialize)]
 struct Permissions {
@@ -360,6 +555,99 @@ impl Permission {
             self.write.remove(position);
         }
     }
+
+    /// Determines whether the given key is covered by this permission's read grants. Entries
+    /// encoding a parent role (see `parent_marker`) are never real kv grants and are skipped.
+    fn allows_read(&self, key: &str) -> bool {
+        self.read
+            .iter()
+            .filter(|granted| parent_name(granted).is_none())
+            .any(|granted| grant_matches(granted, key))
+    }
+
+    /// Determines whether the given key is covered by this permission's write grants.
+    fn allows_write(&self, key: &str) -> bool {
+        self.write.iter().any(|granted| grant_matches(granted, key))
+    }
+}
+
+/// Formats a prefix as the `*`-suffixed pattern etcd uses to grant access to every key sharing
+/// that prefix.
+fn prefix_pattern<K>(prefix: K) -> String
+where
+    K: Into<String>,
+{
+    format!("{}*", prefix.into())
+}
+
+/// Determines whether a granted permission entry covers the given key. An entry ending in `*`
+/// is treated as a prefix match; any other entry must match the key exactly.
+fn grant_matches(granted: &str, key: &str) -> bool {
+    if granted.ends_with('*') {
+        key.starts_with(&granted[..granted.len() - 1])
+    } else {
+        granted == key
+    }
+}
+
+/// The prefix used to encode a parent role's name as an ordinary kv read permission. etcd's
+/// auth schema has no native notion of role inheritance, so a role's parents are persisted as
+/// part of the same `grant`/`revoke` kv permission lists every other permission goes through;
+/// a leading NUL byte keeps the marker from ever colliding with a real etcd key.
+const PARENT_MARKER_PREFIX: &str = "\u{0}parent:";
+
+/// Encodes a parent role's name as a granted read permission.
+fn parent_marker(parent: String) -> String {
+    format!("{}{}", PARENT_MARKER_PREFIX, parent)
+}
+
+/// Decodes a granted read permission back into a parent role's name, if it is one.
+fn parent_name(entry: &str) -> Option<&str> {
+    if entry.starts_with(PARENT_MARKER_PREFIX) {
+        Some(&entry[PARENT_MARKER_PREFIX.len()..])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod grant_matches_tests {
+    use super::grant_matches;
+
+    #[test]
+    fn exact_grant_matches_the_same_key() {
+        assert!(grant_matches("foo", "foo"));
+    }
+
+    #[test]
+    fn exact_grant_does_not_match_a_key_it_is_a_prefix_of() {
+        assert!(!grant_matches("foo", "foobar"));
+    }
+
+    #[test]
+    fn exact_grant_does_not_match_a_different_key() {
+        assert!(!grant_matches("foo", "bar"));
+    }
+
+    #[test]
+    fn wildcard_grant_matches_any_key() {
+        assert!(grant_matches("*", "anything"));
+    }
+
+    #[test]
+    fn prefix_grant_matches_a_key_sharing_the_prefix() {
+        assert!(grant_matches("lab.test.*", "lab.test.suite1"));
+    }
+
+    #[test]
+    fn prefix_grant_matches_the_bare_prefix_itself() {
+        assert!(grant_matches("lab.test.*", "lab.test."));
+    }
+
+    #[test]
+    fn prefix_grant_does_not_match_a_key_missing_the_prefix() {
+        assert!(!grant_matches("lab.test.*", "lab.other.suite1"));
+    }
 }
 
 /// Attempts to disable the auth system.
@@ -502,6 +790,523 @@ where
     Box::new(result)
 }
 
+/// The structure returned by the `GET /v2/auth/users` endpoint.
+#[derive(Debug, Deserialize)]
+struct UserList {
+    /// The users that exist in the cluster.
+    users: Vec<User>,
+}
+
+/// The structure returned by the `GET /v2/auth/roles` endpoint.
+#[derive(Debug, Deserialize)]
+struct RoleList {
+    /// The roles that exist in the cluster.
+    roles: Vec<Role>,
+}
+
+/// Creates a new user.
+pub fn create_user<C>(
+    client: &Client<C>,
+    user: NewUser,
+) -> Box<Future<Item = Response<User>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let path = format!("/users/{}", user.name);
+
+    put_json(client, &path, &user)
+}
+
+/// Returns the user with the given name.
+pub fn get_user<C>(
+    client: &Client<C>,
+    name: &str,
+) -> Box<Future<Item = Response<User>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    get_json(client, &format!("/users/{}", name))
+}
+
+/// Returns all users that exist in the cluster.
+pub fn list_users<C>(
+    client: &Client<C>,
+) -> Box<Future<Item = Response<Vec<User>>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let result = get_json::<C, UserList>(client, "/users").map(|response| Response {
+        data: response.data.users,
+        cluster_info: response.cluster_info,
+    });
+
+    Box::new(result)
+}
+
+/// Updates an existing user.
+pub fn update_user<C>(
+    client: &Client<C>,
+    update: UserUpdate,
+) -> Box<Future<Item = Response<User>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let path = format!("/users/{}", update.name);
+
+    put_json(client, &path, &update)
+}
+
+/// Deletes the user with the given name.
+pub fn delete_user<C>(
+    client: &Client<C>,
+    name: &str,
+) -> Box<Future<Item = Response<()>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    delete(client, &format!("/users/{}", name))
+}
+
+/// Creates a new role.
+pub fn create_role<C>(
+    client: &Client<C>,
+    role: Role,
+) -> Box<Future<Item = Response<Role>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let path = format!("/roles/{}", role.name);
+
+    put_json(client, &path, &role)
+}
+
+/// Returns the role with the given name.
+pub fn get_role<C>(
+    client: &Client<C>,
+    name: &str,
+) -> Box<Future<Item = Response<Role>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    get_json(client, &format!("/roles/{}", name))
+}
+
+/// Returns all roles that exist in the cluster.
+pub fn list_roles<C>(
+    client: &Client<C>,
+) -> Box<Future<Item = Response<Vec<Role>>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let result = get_json::<C, RoleList>(client, "/roles").map(|response| Response {
+        data: response.data.roles,
+        cluster_info: response.cluster_info,
+    });
+
+    Box::new(result)
+}
+
+/// Updates an existing role.
+pub fn update_role<C>(
+    client: &Client<C>,
+    update: RoleUpdate,
+) -> Box<Future<Item = Response<Role>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let path = format!("/roles/{}", update.name);
+
+    put_json(client, &path, &update)
+}
+
+/// Deletes the role with the given name.
+pub fn delete_role<C>(
+    client: &Client<C>,
+    name: &str,
+) -> Box<Future<Item = Response<()>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    delete(client, &format!("/roles/{}", name))
+}
+
+/// A kv store operation to check a user's permissions against.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Action {
+    /// Reading a key.
+    Read,
+    /// Writing a key.
+    Write,
+}
+
+/// Determines whether the named user is allowed to perform `action` against `key`, based
+/// entirely on the permissions granted by the roles currently attached to the user, including
+/// permissions those roles inherit from their parent roles. This lets callers cheaply pre-flight
+/// a kv operation instead of discovering a `403` after the fact.
+///
+/// The user's directly-attached roles come embedded in the `get_user` response, complete with
+/// their own permissions and parents, so only their (as yet unfetched) parent roles require
+/// further requests.
+pub fn enforce<C>(
+    client: &Client<C>,
+    username: &str,
+    key: &str,
+    action: Action,
+) -> Box<Future<Item = Response<bool>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let client = client.clone();
+    let key = key.to_owned();
+
+    let result = get_user(&client, username).and_then(move |response| {
+        let cluster_info = response.cluster_info;
+        let roles = response.data.roles;
+
+        let permission_futures = roles.into_iter().map(|role| {
+            let visited = vec![role.name.clone()];
+            permissions_for_role(client.clone(), role, visited)
+        });
+
+        let result = future::join_all(permission_futures).map(move |role_permissions| {
+            let allowed = role_permissions.iter().any(|permissions| {
+                let (ref read, ref write) = *permissions;
+
+                match action {
+                    Action::Read => read.iter().any(|granted| grant_matches(granted, &key)),
+                    Action::Write => write.iter().any(|granted| grant_matches(granted, &key)),
+                }
+            });
+
+            Response {
+                data: allowed,
+                cluster_info,
+            }
+        });
+
+        Box::new(result) as Box<Future<Item = Response<bool>, Error = Vec<Error>>>
+    });
+
+    Box::new(result)
+}
+
+/// Resolves the read and write key-value permissions a role is effectively granted, as the
+/// union of its own permissions and those of its parent roles (and their parents, recursively).
+/// A role that transitively references itself through `parents` is not visited twice.
+pub fn effective_permissions<C>(
+    client: &Client<C>,
+    role_name: &str,
+) -> Box<Future<Item = Response<(Vec<String>, Vec<String>)>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    collect_effective_permissions(client.clone(), role_name.to_owned(), vec![role_name.to_owned()])
+}
+
+/// Fetches the named role, then resolves its effective permissions via `permissions_for_role`.
+/// `visited` holds the names of every role on the current path, the named role included, so a cycle
+/// stops the recursion instead of looping.
+fn collect_effective_permissions<C>(
+    client: Client<C>,
+    role_name: String,
+    visited: Vec<String>,
+) -> Box<Future<Item = Response<(Vec<String>, Vec<String>)>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let result = get_role(&client, &role_name).and_then(move |response| {
+        let cluster_info = response.cluster_info;
+        let role = response.data;
+
+        permissions_for_role(client, role, visited).map(move |data| Response { data, cluster_info })
+    });
+
+    Box::new(result)
+}
+
+/// Resolves the effective read/write permissions for an already-fetched `role`, recursing into
+/// its unvisited parent roles (via `collect_effective_permissions`, which is the only place a
+/// further `get_role` request is issued) and merging the results.
+fn permissions_for_role<C>(
+    client: Client<C>,
+    role: Role,
+    visited: Vec<String>,
+) -> Box<Future<Item = (Vec<String>, Vec<String>), Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let read = role.kv_read_permissions();
+    let write = role.kv_write_permissions().to_vec();
+
+    let unvisited = unvisited_parents(&role.parents(), &visited);
+
+    if unvisited.is_empty() {
+        return Box::new(Ok(merge_parent_permissions(read, write, Vec::new())).into_future());
+    }
+
+    let parent_futures = unvisited.into_iter().map(|parent| {
+        let mut visited = visited.clone();
+        visited.push(parent.clone());
+
+        collect_effective_permissions(client.clone(), parent, visited)
+    });
+
+    let result = future::join_all(parent_futures).map(move |parent_responses| {
+        let parent_permissions = parent_responses.into_iter().map(|response| response.data).collect();
+        merge_parent_permissions(read, write, parent_permissions)
+    });
+
+    Box::new(result)
+}
+
+/// Returns the names in `parents` that are not already present in `visited`, so a role that
+/// transitively references itself (or an ancestor already on the current path) does not get
+/// fetched, and recursed into, a second time.
+fn unvisited_parents(parents: &[String], visited: &[String]) -> Vec<String> {
+    parents
+        .iter()
+        .filter(|parent| !visited.contains(parent))
+        .cloned()
+        .collect()
+}
+
+/// Unions a role's own read/write permissions with those already resolved for its parents,
+/// sorting and deduplicating the result.
+fn merge_parent_permissions(
+    mut read: Vec<String>,
+    mut write: Vec<String>,
+    parent_permissions: Vec<(Vec<String>, Vec<String>)>,
+) -> (Vec<String>, Vec<String>) {
+    for (parent_read, parent_write) in parent_permissions {
+        read.extend(parent_read);
+        write.extend(parent_write);
+    }
+
+    dedup(&mut read);
+    dedup(&mut write);
+
+    (read, write)
+}
+
+/// Sorts and removes duplicate entries from a list of granted keys.
+fn dedup(keys: &mut Vec<String>) {
+    keys.sort();
+    keys.dedup();
+}
+
+#[cfg(test)]
+mod effective_permissions_tests {
+    use super::{merge_parent_permissions, unvisited_parents};
+
+    #[test]
+    fn unvisited_parents_excludes_already_visited_names() {
+        let parents = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        let visited = vec!["root".to_owned(), "b".to_owned()];
+
+        assert_eq!(unvisited_parents(&parents, &visited), vec!["a".to_owned(), "c".to_owned()]);
+    }
+
+    #[test]
+    fn unvisited_parents_excludes_self_reference() {
+        let parents = vec!["role".to_owned()];
+        let visited = vec!["role".to_owned()];
+
+        assert!(unvisited_parents(&parents, &visited).is_empty());
+    }
+
+    #[test]
+    fn unvisited_parents_stops_a_transitive_cycle() {
+        // "a" -> "b" -> "a": by the time "b" is resolved, "a" is already on the visited path.
+        let parents = vec!["a".to_owned()];
+        let visited = vec!["a".to_owned(), "b".to_owned()];
+
+        assert!(unvisited_parents(&parents, &visited).is_empty());
+    }
+
+    #[test]
+    fn merge_parent_permissions_unions_and_dedups() {
+        let read = vec!["own".to_owned(), "shared".to_owned()];
+        let write = vec!["own-write".to_owned()];
+        let parents = vec![
+            (vec!["shared".to_owned(), "parent-a".to_owned()], vec!["parent-a-write".to_owned()]),
+            (vec!["parent-b".to_owned()], vec!["own-write".to_owned()]),
+        ];
+
+        let (read, write) = merge_parent_permissions(read, write, parents);
+
+        assert_eq!(
+            read,
+            vec!["own".to_owned(), "parent-a".to_owned(), "parent-b".to_owned(), "shared".to_owned()]
+        );
+        assert_eq!(write, vec!["own-write".to_owned(), "parent-a-write".to_owned()]);
+    }
+
+    #[test]
+    fn merge_parent_permissions_handles_a_diamond_shape() {
+        // "root" has parents "left" and "right", which both have parent "shared".
+        let root = (vec!["root-key".to_owned()], Vec::new());
+        let left = (vec!["left-key".to_owned(), "shared-key".to_owned()], Vec::new());
+        let right = (vec!["right-key".to_owned(), "shared-key".to_owned()], Vec::new());
+
+        let (read, _) = merge_parent_permissions(root.0, root.1, vec![left, right]);
+
+        assert_eq!(
+            read,
+            vec![
+                "left-key".to_owned(),
+                "right-key".to_owned(),
+                "root-key".to_owned(),
+                "shared-key".to_owned(),
+            ]
+        );
+    }
+}
+
+/// Issues a `GET` request against the given path and deserializes a `200` response body as
+/// `T`. A `401` or `404` response, like any other non-success status, is surfaced as an
+/// `Error::Api` parsed from the response body (or `Error::Serialization` if the body is not an
+/// API error), the same way `status` handles unexpected statuses.
+fn get_json<C, T>(
+    client: &Client<C>,
+    path: &str,
+) -> Box<Future<Item = Response<T>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+    T: DeserializeOwned + 'static,
+{
+    let http_client = client.http_client().clone();
+    let path = path.to_owned();
+
+    let result = first_ok(client.endpoints().to_vec(), move |member| {
+        let url = build_url(member, &path);
+        let uri = Uri::from_str(url.as_str())
+            .map_err(Error::from)
+            .into_future();
+
+        let http_client = http_client.clone();
+
+        let response = uri.and_then(move |uri| http_client.get(uri).map_err(Error::from));
+
+        let result = response.and_then(|response| {
+            let status = response.status();
+            let cluster_info = ClusterInfo::from(response.headers());
+            let body = response.body().concat2().map_err(Error::from);
+
+            body.and_then(move |ref body| if status == StatusCode::Ok {
+                match serde_json::from_slice::<T>(body) {
+                    Ok(data) => Ok(Response { data, cluster_info }),
+                    Err(error) => Err(Error::Serialization(error)),
+                }
+            } else {
+                Err(parse_api_error(body))
+            })
+        });
+
+        Box::new(result)
+    });
+
+    Box::new(result)
+}
+
+/// Serializes `body` and issues a `PUT` request against the given path, deserializing a `200`
+/// response body as `T`. Mirrors `get_json`'s handling of non-success statuses.
+fn put_json<C, B, T>(
+    client: &Client<C>,
+    path: &str,
+    body: &B,
+) -> Box<Future<Item = Response<T>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+    B: Serialize,
+    T: DeserializeOwned + 'static,
+{
+    let body = match serde_json::to_string(body) {
+        Ok(body) => body,
+        Err(error) => return Box::new(Err(vec![Error::Serialization(error)]).into_future()),
+    };
+
+    let http_client = client.http_client().clone();
+    let path = path.to_owned();
+
+    let result = first_ok(client.endpoints().to_vec(), move |member| {
+        let url = build_url(member, &path);
+        let uri = Uri::from_str(url.as_str())
+            .map_err(Error::from)
+            .into_future();
+
+        let http_client = http_client.clone();
+        let body = body.clone();
+
+        let response = uri.and_then(move |uri| http_client.put(uri, body).map_err(Error::from));
+
+        let result = response.and_then(|response| {
+            let status = response.status();
+            let cluster_info = ClusterInfo::from(response.headers());
+            let body = response.body().concat2().map_err(Error::from);
+
+            body.and_then(move |ref body| if status == StatusCode::Ok {
+                match serde_json::from_slice::<T>(body) {
+                    Ok(data) => Ok(Response { data, cluster_info }),
+                    Err(error) => Err(Error::Serialization(error)),
+                }
+            } else {
+                Err(parse_api_error(body))
+            })
+        });
+
+        Box::new(result)
+    });
+
+    Box::new(result)
+}
+
+/// Issues a `DELETE` request against the given path. Mirrors `get_json`'s handling of
+/// non-success statuses.
+fn delete<C>(client: &Client<C>, path: &str) -> Box<Future<Item = Response<()>, Error = Vec<Error>>>
+where
+    C: Clone + Connect,
+{
+    let http_client = client.http_client().clone();
+    let path = path.to_owned();
+
+    let result = first_ok(client.endpoints().to_vec(), move |member| {
+        let url = build_url(member, &path);
+        let uri = Uri::from_str(url.as_str())
+            .map_err(Error::from)
+            .into_future();
+
+        let http_client = http_client.clone();
+
+        let response = uri.and_then(move |uri| http_client.delete(uri).map_err(Error::from));
+
+        let result = response.and_then(|response| {
+            let status = response.status();
+            let cluster_info = ClusterInfo::from(response.headers());
+            let body = response.body().concat2().map_err(Error::from);
+
+            body.and_then(move |ref body| if status == StatusCode::Ok {
+                Ok(Response { data: (), cluster_info })
+            } else {
+                Err(parse_api_error(body))
+            })
+        });
+
+        Box::new(result)
+    });
+
+    Box::new(result)
+}
+
+/// Parses a non-success response body as an `ApiError`, the way `status` does for its own
+/// unexpected statuses. This is how a `401` (unauthorized) or `404` (not found) response from
+/// the users and roles endpoints is surfaced to callers.
+fn parse_api_error(body: &[u8]) -> Error {
+    match serde_json::from_slice::<ApiError>(body) {
+        Ok(error) => Error::Api(error),
+        Err(error) => Error::Serialization(error),
+    }
+}
+
 /// Constructs the full URL for an API call.
 fn build_url(endpoint: &Uri, path: &str) -> String {
     let maybe_slash = if endpoint.as_ref().ends_with("/") {